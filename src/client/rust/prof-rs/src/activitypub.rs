@@ -0,0 +1,247 @@
+//! Renders a `Profile` as an ActivityPub actor document, plus a WebFinger
+//! helper that points back at it. This lets `prof` profiles federate with
+//! ActivityPub servers (Mastodon and friends) without exposing any of the
+//! signing machinery used for `prof`'s own endpoints.
+
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Serialize};
+
+use crate::{ProfError, Profile, Sessionless};
+
+// DER encodings of the two OIDs that make up an EC SubjectPublicKeyInfo's
+// AlgorithmIdentifier for a secp256k1 key: id-ecPublicKey (1.2.840.10045.2.1)
+// and the secp256k1 named curve (1.3.132.0.10).
+const EC_PUBLIC_KEY_OID: [u8; 9] = [0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+const SECP256K1_OID: [u8; 7] = [0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x0a];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ActivityPubActor {
+    #[serde(rename = "@context")]
+    pub context: &'static str,
+    pub id: String,
+    #[serde(rename = "type")]
+    pub actor_type: &'static str,
+    pub inbox: String,
+    pub outbox: String,
+    pub followers: String,
+    #[serde(rename = "preferredUsername")]
+    pub preferred_username: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    pub icon: ActivityPubIcon,
+    #[serde(rename = "publicKey")]
+    pub public_key: ActivityPubPublicKey,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ActivityPubIcon {
+    #[serde(rename = "type")]
+    pub icon_type: &'static str,
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ActivityPubPublicKey {
+    pub id: String,
+    pub owner: String,
+    #[serde(rename = "publicKeyPem")]
+    pub public_key_pem: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebFingerResource {
+    pub subject: String,
+    pub aliases: Vec<String>,
+    pub links: Vec<WebFingerLink>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebFingerLink {
+    pub rel: String,
+    #[serde(rename = "type")]
+    pub link_type: String,
+    pub href: String,
+}
+
+impl Profile {
+    /// Renders this profile as a JSON-LD ActivityPub `Person` actor, so it
+    /// can be served at the profile's `id` URL for remote servers to fetch.
+    /// `base_url` is the public origin `prof` is served from (no trailing
+    /// slash required); `sessionless` is the identity that signs this
+    /// profile's requests, from which the actor's `publicKey` is derived so
+    /// it's actually tied to the key remote servers would need to verify
+    /// signatures from this actor.
+    pub fn to_activitypub_actor(&self, base_url: &str, sessionless: &Sessionless) -> Result<ActivityPubActor, ProfError> {
+        let base_url = base_url.trim_end_matches('/');
+        let actor_id = format!("{}/user/{}", base_url, self.uuid);
+        let image_url = format!("{}/user/{}/profile/image", base_url, self.uuid);
+        let public_key_pem = public_key_to_pem(sessionless)?;
+
+        Ok(ActivityPubActor {
+            context: "https://www.w3.org/ns/activitystreams",
+            inbox: format!("{}/inbox", actor_id),
+            outbox: format!("{}/outbox", actor_id),
+            followers: format!("{}/followers", actor_id),
+            actor_type: "Person",
+            preferred_username: webfinger_username(&self.name),
+            name: self.name.clone(),
+            summary: self.additional_fields.get("bio")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string()),
+            icon: ActivityPubIcon {
+                icon_type: "Image",
+                url: image_url,
+            },
+            public_key: ActivityPubPublicKey {
+                id: format!("{}#main-key", actor_id),
+                owner: actor_id.clone(),
+                public_key_pem,
+            },
+            id: actor_id,
+        })
+    }
+
+    /// Builds the WebFinger resource descriptor (the JSON served from
+    /// `/.well-known/webfinger?resource=acct:name@domain`) that links an
+    /// `acct:` identifier back to this profile's actor document. The
+    /// `acct:` user-part is derived from `name` the same way as
+    /// `to_activitypub_actor`'s `preferredUsername`, since `acct:` URIs
+    /// can't contain the spaces a free-text display name may have.
+    pub fn to_webfinger(&self, base_url: &str) -> WebFingerResource {
+        let base_url = base_url.trim_end_matches('/');
+        let actor_id = format!("{}/user/{}", base_url, self.uuid);
+        let domain = base_url
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .split('/')
+            .next()
+            .unwrap_or(base_url);
+
+        WebFingerResource {
+            subject: format!("acct:{}@{}", webfinger_username(&self.name), domain),
+            aliases: vec![actor_id.clone()],
+            links: vec![WebFingerLink {
+                rel: "self".to_string(),
+                link_type: "application/activity+json".to_string(),
+                href: actor_id,
+            }],
+        }
+    }
+}
+
+/// Slugifies a free-text display name into a WebFinger/ActivityPub-safe
+/// user-part: lowercased, non-alphanumeric runs collapsed to a single `-`.
+/// Falls back to `"user"` if nothing alphanumeric survives.
+fn webfinger_username(name: &str) -> String {
+    let slug: String = name
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let slug = slug.split('-').filter(|s| !s.is_empty()).collect::<Vec<_>>().join("-");
+
+    if slug.is_empty() {
+        "user".to_string()
+    } else {
+        slug
+    }
+}
+
+/// Converts a sessionless secp256k1 public key into a PEM-encoded
+/// SubjectPublicKeyInfo block, so the `publicKey` on an ActivityPub actor
+/// document is actually derived from the identity that signs this client's
+/// requests rather than supplied out of band.
+fn public_key_to_pem(sessionless: &Sessionless) -> Result<String, ProfError> {
+    let point = sessionless::hex::decode(sessionless.public_key().to_hex())
+        .map_err(|e| ProfError::Auth(format!("invalid sessionless public key: {e}")))?;
+
+    let mut algorithm = Vec::new();
+    algorithm.extend_from_slice(&EC_PUBLIC_KEY_OID);
+    algorithm.extend_from_slice(&SECP256K1_OID);
+
+    let mut spki = Vec::new();
+    spki.extend(der_sequence(&algorithm));
+    spki.extend(der_bit_string(&point));
+    let spki = der_sequence(&spki);
+
+    let encoded = BASE64.encode(spki);
+    let mut pem = String::from("-----BEGIN PUBLIC KEY-----\n");
+    for line in encoded.as_bytes().chunks(64) {
+        pem.push_str(std::str::from_utf8(line).unwrap());
+        pem.push('\n');
+    }
+    pem.push_str("-----END PUBLIC KEY-----\n");
+
+    Ok(pem)
+}
+
+fn der_length(len: usize) -> Vec<u8> {
+    if len < 0x80 {
+        vec![len as u8]
+    } else {
+        let be_bytes: Vec<u8> = len.to_be_bytes().iter().copied().skip_while(|&b| b == 0).collect();
+        let mut out = vec![0x80 | be_bytes.len() as u8];
+        out.extend(be_bytes);
+        out
+    }
+}
+
+fn der_sequence(contents: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x30];
+    out.extend(der_length(contents.len()));
+    out.extend_from_slice(contents);
+    out
+}
+
+fn der_bit_string(bytes: &[u8]) -> Vec<u8> {
+    let mut contents = vec![0x00]; // no unused bits in the last byte
+    contents.extend_from_slice(bytes);
+    let mut out = vec![0x03];
+    out.extend(der_length(contents.len()));
+    out.extend(contents);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn profile(name: &str) -> Profile {
+        Profile {
+            uuid: "abc123".to_string(),
+            name: name.to_string(),
+            email: "user@example.com".to_string(),
+            image_filename: None,
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            updated_at: "2026-01-01T00:00:00Z".to_string(),
+            additional_fields: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_webfinger_username_slugifies_display_name() {
+        assert_eq!(webfinger_username("John Doe"), "john-doe");
+        assert_eq!(webfinger_username("  Jane  Q. Public! "), "jane-q-public");
+        assert_eq!(webfinger_username("***"), "user");
+    }
+
+    #[test]
+    fn test_to_webfinger_subject_has_no_spaces() {
+        let resource = profile("John Doe").to_webfinger("https://prof.example");
+        assert_eq!(resource.subject, "acct:john-doe@prof.example");
+    }
+
+    #[test]
+    fn test_to_activitypub_actor_derives_public_key_pem() {
+        let sessionless = Sessionless::new();
+        let actor = profile("John Doe").to_activitypub_actor("https://prof.example", &sessionless).unwrap();
+
+        assert_eq!(actor.preferred_username, "john-doe");
+        assert!(actor.public_key.public_key_pem.starts_with("-----BEGIN PUBLIC KEY-----\n"));
+        assert!(actor.public_key.public_key_pem.ends_with("-----END PUBLIC KEY-----\n"));
+    }
+}
@@ -0,0 +1,201 @@
+//! Bulk profile import/export, for provisioning or backing up many profiles
+//! at once without the caller reimplementing concurrency, error
+//! aggregation, and NDJSON archival around the single-profile methods.
+
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use futures::stream::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{ProfClient, ProfError, Profile, Sessionless};
+
+/// Filename recorded for exported image data whose profile has no
+/// `imageFilename` of its own, so `export_profiles` never produces an
+/// archive record that `parse_profile_archive` would silently drop the
+/// image from.
+const DEFAULT_IMAGE_FILENAME: &str = "image.jpg";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BulkArchiveRecord {
+    #[serde(flatten)]
+    profile_data: HashMap<String, serde_json::Value>,
+    #[serde(rename = "imageFilename", skip_serializing_if = "Option::is_none")]
+    image_filename: Option<String>,
+    #[serde(rename = "imageBase64", skip_serializing_if = "Option::is_none")]
+    image_base64: Option<String>,
+}
+
+/// Parses an NDJSON archive produced by `ProfClient::export_profiles` back
+/// into the `(profile_data, image_data)` tuples `import_profiles` expects.
+pub fn parse_profile_archive(archive: &str) -> Result<Vec<(HashMap<String, serde_json::Value>, Option<(Vec<u8>, String)>)>, ProfError> {
+    let mut items = Vec::new();
+
+    for line in archive.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let record: BulkArchiveRecord = serde_json::from_str(line)?;
+        let image_data = match (record.image_base64, record.image_filename) {
+            (Some(b64), Some(filename)) => {
+                let bytes = BASE64.decode(b64).map_err(|e| ProfError::Service(format!("Invalid image data in archive: {e}")))?;
+                Some((bytes, filename))
+            },
+            _ => None,
+        };
+
+        items.push((record.profile_data, image_data));
+    }
+
+    Ok(items)
+}
+
+fn profile_to_data(profile: &Profile) -> HashMap<String, serde_json::Value> {
+    let mut data = profile.additional_fields.clone();
+    data.insert("name".to_string(), serde_json::Value::String(profile.name.clone()));
+    data.insert("email".to_string(), serde_json::Value::String(profile.email.clone()));
+    data
+}
+
+impl ProfClient {
+    /// Drives `create_profile` over `items` through a bounded concurrency
+    /// limiter, returning a per-item result instead of aborting on the
+    /// first failure. Each item carries its own `Sessionless` identity:
+    /// this auth model addresses a profile by the hex-encoded public key
+    /// that signs for it, so provisioning N distinct profiles takes N
+    /// distinct keypairs -- signing every import as `self`'s own identity
+    /// would make every item race to overwrite the same profile. Callers
+    /// typically zip `parse_profile_archive`'s output with a matching list
+    /// of identities (the archive format itself never carries private
+    /// keys). Results are tagged with (and sorted by) their index in
+    /// `items`, since `buffer_unordered` completes them in whatever order
+    /// the server responds rather than the order they were submitted.
+    pub async fn import_profiles<S>(&self, items: S, concurrency: usize) -> Vec<(usize, Result<Profile, ProfError>)>
+    where
+        S: Stream<Item = (Sessionless, HashMap<String, serde_json::Value>, Option<(Vec<u8>, String)>)>,
+    {
+        let results: Vec<(usize, Result<Profile, ProfError>)> = items
+            .enumerate()
+            .map(|(index, (sessionless, profile_data, image_data))| async move {
+                let item_client = self.with_identity(sessionless);
+                (index, item_client.create_profile(profile_data, image_data).await)
+            })
+            .buffer_unordered(concurrency.max(1))
+            .collect()
+            .await;
+
+        sort_by_index(results)
+    }
+
+    /// Fetches each profile in `uuids` plus its image bytes (if any) and
+    /// serializes them to a newline-delimited JSON archive that
+    /// `parse_profile_archive`/`import_profiles` can consume.
+    pub async fn export_profiles(&self, uuids: &[&str]) -> Result<String, ProfError> {
+        let mut archive = String::new();
+
+        for uuid in uuids {
+            let profile = self.get_profile(Some(uuid)).await?;
+            let (image_filename, image_base64) = match self.get_profile_image(Some(uuid)).await {
+                Ok(bytes) => {
+                    // profile.image_filename may be unset even when the
+                    // image itself fetches successfully; fall back to a
+                    // default name so the record always pairs a filename
+                    // with its image data, since parse_profile_archive only
+                    // reconstructs image data when both are present.
+                    let filename = profile.image_filename.clone().unwrap_or_else(|| DEFAULT_IMAGE_FILENAME.to_string());
+                    (Some(filename), Some(BASE64.encode(bytes)))
+                }
+                Err(ProfError::NotFound(_)) => (None, None),
+                Err(e) => return Err(e),
+            };
+
+            let record = BulkArchiveRecord {
+                profile_data: profile_to_data(&profile),
+                image_filename,
+                image_base64,
+            };
+
+            archive.push_str(&serde_json::to_string(&record)?);
+            archive.push('\n');
+        }
+
+        Ok(archive)
+    }
+}
+
+/// Restores input order to a set of `(index, result)` pairs that may have
+/// completed out of order (e.g. from `buffer_unordered`).
+fn sort_by_index<T>(mut indexed: Vec<(usize, T)>) -> Vec<(usize, T)> {
+    indexed.sort_by_key(|(index, _)| *index);
+    indexed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort_by_index_restores_submission_order_from_completion_order() {
+        // Simulates three items completing out of order (item 2 first, then
+        // 0, then 1) -- the kind of interleaving `buffer_unordered` produces.
+        let completed = vec![(2usize, "c"), (0usize, "a"), (1usize, "b")];
+        let restored = sort_by_index(completed);
+        assert_eq!(restored, vec![(0, "a"), (1, "b"), (2, "c")]);
+    }
+
+    #[test]
+    fn test_sort_by_index_keeps_each_result_tied_to_its_item() {
+        let completed: Vec<(usize, Result<i32, &str>)> =
+            vec![(1, Err("boom")), (0, Ok(10)), (2, Ok(30))];
+        let restored = sort_by_index(completed);
+
+        assert_eq!(restored[0], (0, Ok(10)));
+        assert_eq!(restored[1], (1, Err("boom")));
+        assert_eq!(restored[2], (2, Ok(30)));
+    }
+
+    #[test]
+    fn test_parse_profile_archive_round_trips_profile_data_and_image() {
+        let archive = r#"{"name":"Ada","email":"ada@example.com","imageFilename":"image.jpg","imageBase64":"aGVsbG8="}
+"#;
+        let items = parse_profile_archive(archive).unwrap();
+        assert_eq!(items.len(), 1);
+
+        let (profile_data, image_data) = &items[0];
+        assert_eq!(profile_data.get("name").unwrap(), &serde_json::Value::String("Ada".to_string()));
+
+        let (bytes, filename) = image_data.as_ref().unwrap();
+        assert_eq!(filename, "image.jpg");
+        assert_eq!(bytes, b"hello");
+    }
+
+    #[test]
+    fn test_parse_profile_archive_skips_blank_lines_and_missing_image() {
+        let archive = "\n{\"name\":\"Ada\",\"email\":\"ada@example.com\"}\n\n";
+        let items = parse_profile_archive(archive).unwrap();
+        assert_eq!(items.len(), 1);
+        assert!(items[0].1.is_none());
+    }
+
+    #[test]
+    fn test_parse_profile_archive_keeps_image_under_default_filename() {
+        // Mirrors what export_profiles now writes when a profile has image
+        // bytes but no recorded imageFilename: without the fallback,
+        // parse_profile_archive's (Some, Some) match would never fire and
+        // the image would silently disappear on import.
+        let record = BulkArchiveRecord {
+            profile_data: HashMap::new(),
+            image_filename: Some(DEFAULT_IMAGE_FILENAME.to_string()),
+            image_base64: Some(BASE64.encode(b"hello")),
+        };
+        let archive = serde_json::to_string(&record).unwrap();
+
+        let items = parse_profile_archive(&archive).unwrap();
+        let (_, image_data) = &items[0];
+        let (bytes, filename) = image_data.as_ref().unwrap();
+        assert_eq!(filename, DEFAULT_IMAGE_FILENAME);
+        assert_eq!(bytes, b"hello");
+    }
+}
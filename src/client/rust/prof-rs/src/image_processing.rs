@@ -0,0 +1,122 @@
+//! Client-side image validation and transcoding, gated behind the `image`
+//! feature. Decodes and re-encodes uploaded images instead of trusting the
+//! caller-provided filename extension, so `create_profile`/`update_profile`
+//! never forward bytes that merely *claim* to be a JPEG/PNG/WebP.
+
+use image::{imageops::FilterType, ImageFormat, ImageOutputFormat};
+
+use crate::ProfError;
+
+/// Maximum dimension (in pixels, applied to the longer side) and maximum
+/// encoded size an uploaded image is allowed to have after processing.
+#[derive(Debug, Clone, Copy)]
+pub struct ImageLimits {
+    pub max_dim: u32,
+    pub max_bytes: usize,
+}
+
+/// The result of running an uploaded image through [`process_image`]: a
+/// re-encoded, size-bounded image with its metadata stripped.
+#[derive(Debug, Clone)]
+pub struct ProcessedImage {
+    pub bytes: Vec<u8>,
+    pub mime_type: &'static str,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Decodes `bytes`, sniffing the real format from its magic bytes rather
+/// than trusting a filename extension, downscales it to fit within
+/// `limits.max_dim` on its longer side (preserving aspect ratio), strips
+/// EXIF/metadata by re-encoding to JPEG, and enforces `limits.max_bytes` on
+/// the result.
+pub fn process_image(bytes: &[u8], limits: ImageLimits) -> Result<ProcessedImage, ProfError> {
+    let format = image::guess_format(bytes)
+        .map_err(|e| ProfError::InvalidImage(format!("unrecognized image data: {e}")))?;
+
+    if !matches!(format, ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP) {
+        return Err(ProfError::InvalidImage(format!(
+            "unsupported image format: {:?}",
+            format
+        )));
+    }
+
+    let decoded = image::load_from_memory_with_format(bytes, format)
+        .map_err(|e| ProfError::InvalidImage(format!("failed to decode image: {e}")))?;
+
+    let (width, height) = (decoded.width(), decoded.height());
+    let resized = if width > limits.max_dim || height > limits.max_dim {
+        decoded.resize(limits.max_dim, limits.max_dim, FilterType::Lanczos3)
+    } else {
+        decoded
+    };
+
+    // Re-encoding to a fresh JPEG drops any EXIF/metadata carried by the
+    // original bytes.
+    let mut encoded = Vec::new();
+    resized
+        .write_to(&mut std::io::Cursor::new(&mut encoded), ImageOutputFormat::Jpeg(85))
+        .map_err(|e| ProfError::InvalidImage(format!("failed to re-encode image: {e}")))?;
+
+    if encoded.len() > limits.max_bytes {
+        return Err(ProfError::InvalidImage(format!(
+            "encoded image is {} bytes, exceeds limit of {} bytes",
+            encoded.len(),
+            limits.max_bytes
+        )));
+    }
+
+    Ok(ProcessedImage {
+        width: resized.width(),
+        height: resized.height(),
+        bytes: encoded,
+        mime_type: "image/jpeg",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_fixture(width: u32, height: u32) -> Vec<u8> {
+        let image = image::RgbImage::from_pixel(width, height, image::Rgb([200, 10, 10]));
+        let mut bytes = Vec::new();
+        image::DynamicImage::ImageRgb8(image)
+            .write_to(&mut std::io::Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_process_image_downscales_oversized_image() {
+        let limits = ImageLimits { max_dim: 64, max_bytes: 1_000_000 };
+        let processed = process_image(&png_fixture(200, 100), limits).unwrap();
+
+        assert_eq!(processed.mime_type, "image/jpeg");
+        assert_eq!(processed.width, 64);
+        assert_eq!(processed.height, 32);
+    }
+
+    #[test]
+    fn test_process_image_leaves_small_image_dimensions_untouched() {
+        let limits = ImageLimits { max_dim: 64, max_bytes: 1_000_000 };
+        let processed = process_image(&png_fixture(32, 16), limits).unwrap();
+
+        assert_eq!(processed.width, 32);
+        assert_eq!(processed.height, 16);
+    }
+
+    #[test]
+    fn test_process_image_rejects_undersized_byte_limit() {
+        let limits = ImageLimits { max_dim: 64, max_bytes: 10 };
+        let err = process_image(&png_fixture(32, 16), limits).unwrap_err();
+        assert!(matches!(err, ProfError::InvalidImage(_)));
+    }
+
+    #[test]
+    fn test_process_image_rejects_garbage_bytes() {
+        let limits = ImageLimits { max_dim: 64, max_bytes: 1_000_000 };
+        let err = process_image(b"not an image", limits).unwrap_err();
+        assert!(matches!(err, ProfError::InvalidImage(_)));
+    }
+}
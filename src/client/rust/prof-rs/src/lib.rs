@@ -1,11 +1,27 @@
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use rand::Rng;
 use reqwest::{Client, multipart};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::time::Duration;
 use thiserror::Error;
 use uuid::Uuid;
 
 pub use sessionless::{Sessionless, hex::IntoHex};
 
+#[cfg(feature = "image")]
+mod image_processing;
+#[cfg(feature = "image")]
+pub use image_processing::{ImageLimits, ProcessedImage};
+
+mod activitypub;
+pub use activitypub::{ActivityPubActor, ActivityPubIcon, ActivityPubPublicKey, WebFingerLink, WebFingerResource};
+
+mod bulk;
+pub use bulk::parse_profile_archive;
+
 #[derive(Error, Debug)]
 pub enum ProfError {
     #[error("HTTP request failed: {0}")]
@@ -20,6 +36,13 @@ pub enum ProfError {
     NotFound(String),
     #[error("Validation failed: {errors:?}")]
     Validation { errors: Vec<String> },
+    #[cfg(feature = "image")]
+    #[error("Invalid image: {0}")]
+    InvalidImage(String),
+    #[error("Rate limited{}", retry_after.map(|d| format!(", retry after {:?}", d)).unwrap_or_default())]
+    RateLimited { retry_after: Option<Duration> },
+    #[error("Request timed out")]
+    Timeout,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -61,10 +84,133 @@ pub struct MagicResponse {
     pub data: HashMap<String, serde_json::Value>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct ProfilesResponse {
+    success: bool,
+    profiles: Option<Vec<Profile>>,
+    error: Option<String>,
+    #[serde(default)]
+    links: Option<PageLinks>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PageLinks {
+    next: Option<String>,
+    prev: Option<String>,
+}
+
+/// Filters and pagination knobs for `ProfClient::list_profiles` /
+/// `search_profiles`. Mirrors the consuming-builder style of
+/// [`ProfileBuilder`].
+#[derive(Debug, Clone, Default)]
+pub struct ProfileSearch {
+    name_prefix: Option<String>,
+    email_domain: Option<String>,
+    additional_fields: HashMap<String, String>,
+    page_size: Option<u32>,
+}
+
+impl ProfileSearch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn name_prefix(mut self, prefix: &str) -> Self {
+        self.name_prefix = Some(prefix.to_string());
+        self
+    }
+
+    pub fn email_domain(mut self, domain: &str) -> Self {
+        self.email_domain = Some(domain.to_string());
+        self
+    }
+
+    pub fn field(mut self, key: &str, value: &str) -> Self {
+        self.additional_fields.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = Some(page_size);
+        self
+    }
+
+    fn into_query_params(self) -> Vec<(String, String)> {
+        let mut params = Vec::new();
+        if let Some(prefix) = self.name_prefix {
+            params.push(("namePrefix".to_string(), prefix));
+        }
+        if let Some(domain) = self.email_domain {
+            params.push(("emailDomain".to_string(), domain));
+        }
+        if let Some(page_size) = self.page_size {
+            params.push(("pageSize".to_string(), page_size.to_string()));
+        }
+        params.extend(self.additional_fields);
+        params
+    }
+}
+
+/// One page of a profile listing, with cursor URLs to continue iterating the
+/// directory without constructing request URLs by hand.
+#[derive(Debug, Clone)]
+pub struct ProfilePage {
+    pub profiles: Vec<Profile>,
+    next: Option<String>,
+    prev: Option<String>,
+}
+
+impl ProfilePage {
+    pub fn has_next(&self) -> bool {
+        self.next.is_some()
+    }
+
+    pub fn has_prev(&self) -> bool {
+        self.prev.is_some()
+    }
+
+    pub async fn next_page(&self, client: &ProfClient) -> Result<Option<ProfilePage>, ProfError> {
+        match &self.next {
+            Some(cursor) => Ok(Some(client.fetch_profile_page(cursor).await?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn prev_page(&self, client: &ProfClient) -> Result<Option<ProfilePage>, ProfError> {
+        match &self.prev {
+            Some(cursor) => Ok(Some(client.fetch_profile_page(cursor).await?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Governs retry behavior for idempotent requests (GET profile/image,
+/// health). `max_attempts` counts the initial try, so `1` (the default)
+/// means no retries.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
 pub struct ProfClient {
     base_url: String,
     client: Client,
     sessionless: Option<Sessionless>,
+    retry_policy: RetryPolicy,
+    #[cfg(feature = "image")]
+    image_limits: Option<ImageLimits>,
 }
 
 impl ProfClient {
@@ -79,6 +225,9 @@ impl ProfClient {
             base_url,
             client: Client::new(),
             sessionless: None,
+            retry_policy: RetryPolicy::default(),
+            #[cfg(feature = "image")]
+            image_limits: None,
         }
     }
 
@@ -91,7 +240,71 @@ impl ProfClient {
         self.sessionless = Some(sessionless);
     }
 
-    fn get_auth_params(&self) -> Result<HashMap<String, String>, ProfError> {
+    /// Builds a client that shares this client's base URL, HTTP client,
+    /// retry policy, and image limits but signs as a different identity.
+    /// This auth model addresses a profile by the hex-encoded public key
+    /// that signs for it, so `bulk::import_profiles` uses this to give
+    /// each imported profile its own keypair rather than racing every
+    /// import against `self`'s own `/user/<uuid>/profile`.
+    pub(crate) fn with_identity(&self, sessionless: Sessionless) -> ProfClient {
+        ProfClient {
+            base_url: self.base_url.clone(),
+            client: self.client.clone(),
+            sessionless: Some(sessionless),
+            retry_policy: self.retry_policy.clone(),
+            #[cfg(feature = "image")]
+            image_limits: self.image_limits,
+        }
+    }
+
+    /// Sets the retry policy applied to idempotent requests (GET
+    /// profile/image, health). Defaults to no retries.
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Sets a request timeout applied to every request made by this client.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = Client::builder()
+            .timeout(timeout)
+            .build()
+            .unwrap_or_else(|_| Client::new());
+        self
+    }
+
+    /// Enables client-side image validation/transcoding for subsequent
+    /// `create_profile`/`update_profile` calls: uploaded bytes are decoded,
+    /// sniffed from their magic bytes, downscaled to fit within `max_dim` on
+    /// their longer side, stripped of metadata, and re-encoded, rejecting
+    /// anything that doesn't decode as a real image or that still exceeds
+    /// `max_bytes` afterwards.
+    #[cfg(feature = "image")]
+    pub fn with_image_limits(mut self, max_dim: u32, max_bytes: usize) -> Self {
+        self.image_limits = Some(ImageLimits { max_dim, max_bytes });
+        self
+    }
+
+    /// Returns the signer's own uuid (the hex-encoded sessionless public key)
+    /// without performing a full signing pass. Callers use this to build the
+    /// request path that then gets fed into `get_auth_params`.
+    fn own_uuid(&self) -> Result<String, ProfError> {
+        let sessionless = self.sessionless.as_ref()
+            .ok_or_else(|| ProfError::Auth("Sessionless not configured".to_string()))?;
+        Ok(sessionless.public_key().to_hex())
+    }
+
+    /// Builds the sessionless auth params for a request, binding the signature
+    /// to the method, path, query string, and body (HTTP-signature style) so
+    /// a captured signature/timestamp pair can't be replayed against a
+    /// different endpoint *or* a different set of query parameters. `query`
+    /// should be the canonical query string (see `canonical_query_string`)
+    /// of any caller-controlled parameters -- not including the auth params
+    /// themselves, which don't exist yet at signing time -- or `""` for
+    /// requests that don't carry any. `body` should be the exact bytes that
+    /// will be sent on the wire (or an empty slice for requests without
+    /// one); the digest is computed over exactly those bytes.
+    fn get_auth_params(&self, method: &str, path: &str, query: &str, body: &[u8]) -> Result<HashMap<String, String>, ProfError> {
         let sessionless = self.sessionless.as_ref()
             .ok_or_else(|| ProfError::Auth("Sessionless not configured".to_string()))?;
 
@@ -103,96 +316,95 @@ impl ProfClient {
 
         let hash = Uuid::new_v4().to_string();
         let uuid = sessionless.public_key().to_hex();
-        
-        // Create message to sign from timestamp
-        let signature = sessionless.sign(&timestamp);
+
+        let mut digest_hasher = Sha256::new();
+        digest_hasher.update(body);
+        let digest = format!("SHA-256={}", BASE64.encode(digest_hasher.finalize()));
+
+        // Canonical signing string binds the signature to this exact
+        // request instead of just the timestamp.
+        let signing_string = format!(
+            "timestamp: {}\nmethod: {}\npath: {}\nquery: {}\ndigest: {}",
+            timestamp, method, path, query, digest
+        );
+        let signature = sessionless.sign(&signing_string);
 
         let mut params = HashMap::new();
         params.insert("uuid".to_string(), uuid);
         params.insert("timestamp".to_string(), timestamp);
         params.insert("hash".to_string(), hash);
         params.insert("signature".to_string(), signature.to_hex());
+        params.insert("digest".to_string(), digest);
+        params.insert("signedFields".to_string(), "timestamp method path query digest".to_string());
 
         Ok(params)
     }
 
-    pub async fn create_profile(
-        &self,
-        profile_data: HashMap<String, serde_json::Value>,
-        image_data: Option<(Vec<u8>, String)>, // (bytes, filename)
-    ) -> Result<Profile, ProfError> {
-        let auth = self.get_auth_params()?;
-        let uuid = auth.get("uuid").unwrap();
-
-        let url = format!("{}/user/{}/profile", self.base_url, uuid);
-
-        let mut form = multipart::Form::new();
-
-        // Add profile data
-        form = form.text("profileData", serde_json::to_string(&profile_data)?);
-
-        // Add auth parameters
-        for (key, value) in auth {
-            form = form.text(key, value);
-        }
-
-        // Add image if provided
-        if let Some((image_bytes, filename)) = image_data {
-            let part = multipart::Part::bytes(image_bytes)
-                .file_name(filename.clone())
-                .mime_str(&self.guess_mime_type(&filename))?;
-            form = form.part("image", part);
+    /// Sends a request once, classifying transport-level failures (timeouts
+    /// vs. other transport errors) into `ProfError`.
+    async fn send_once(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, ProfError> {
+        match request.send().await {
+            Ok(response) => Ok(response),
+            Err(e) if e.is_timeout() => Err(ProfError::Timeout),
+            Err(e) => Err(ProfError::Http(e)),
         }
+    }
 
-        let response = self.client
-            .post(&url)
-            .multipart(form)
-            .send()
-            .await?;
-
-        let status = response.status();
-        let response_text = response.text().await?;
-        
-        // Try to parse as ProfileResponse first (successful response)
-        let response_data: ProfileResponse = if let Ok(parsed) = serde_json::from_str(&response_text) {
-            parsed
-        } else {
-            // If that fails, try to parse as a simple error response
-            if let Ok(error_obj) = serde_json::from_str::<serde_json::Value>(&response_text) {
-                if let Some(error_msg) = error_obj.get("error").and_then(|e| e.as_str()) {
-                    ProfileResponse {
-                        success: false,
-                        profile: None,
-                        error: Some(error_msg.to_string()),
-                        details: error_obj.get("details")
-                            .and_then(|d| d.as_array())
-                            .map(|arr| arr.iter()
-                                .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                                .collect()),
+    /// Sends an idempotent request (GET profile/image, health), retrying
+    /// transient failures per `self.retry_policy`: a `429`/`503` honors the
+    /// server's `Retry-After` header, a bare 5xx or a timeout backs off with
+    /// jitter, and `build` is called again on each attempt since requests
+    /// can't be replayed.
+    async fn send_idempotent<F>(&self, build: F) -> Result<reqwest::Response, ProfError>
+    where
+        F: Fn() -> reqwest::RequestBuilder,
+    {
+        let mut attempt = 0u32;
+        loop {
+            attempt += 1;
+            match self.send_once(build()).await {
+                Ok(response) => {
+                    let status = response.status();
+                    if status.as_u16() == 429 || status.as_u16() == 503 {
+                        let retry_after = parse_retry_after(&response);
+                        if attempt < self.retry_policy.max_attempts {
+                            tokio::time::sleep(retry_after.unwrap_or_else(|| self.backoff_delay(attempt))).await;
+                            continue;
+                        }
+                        return Err(ProfError::RateLimited { retry_after });
                     }
-                } else {
-                    return Err(ProfError::Service(format!("Invalid response format: {}", response_text)));
+                    if status.is_server_error() && attempt < self.retry_policy.max_attempts {
+                        tokio::time::sleep(self.backoff_delay(attempt)).await;
+                        continue;
+                    }
+                    return Ok(response);
                 }
-            } else {
-                return Err(ProfError::Service(format!("Could not parse response: {}", response_text)));
+                Err(ProfError::Timeout) if attempt < self.retry_policy.max_attempts => {
+                    tokio::time::sleep(self.backoff_delay(attempt)).await;
+                    continue;
+                }
+                Err(e) => return Err(e),
             }
-        };
-
-        if !response_data.success {
-            return match status.as_u16() {
-                400 => {
-                    if let Some(details) = response_data.details {
-                        Err(ProfError::Validation { errors: details })
-                    } else {
-                        Err(ProfError::Service(response_data.error.unwrap_or_else(|| "Validation failed".to_string())))
-                    }
-                },
-                404 => Err(ProfError::NotFound(response_data.error.unwrap_or_else(|| "Not found".to_string()))),
-                _ => Err(ProfError::Service(response_data.error.unwrap_or_else(|| "Unknown error".to_string()))),
-            };
         }
+    }
 
-        response_data.profile.ok_or_else(|| ProfError::Service("No profile in response".to_string()))
+    /// Full-jitter exponential backoff: a random delay between zero and
+    /// `min(max_delay, base_delay * 2^(attempt - 1))`.
+    fn backoff_delay(&self, attempt: u32) -> Duration {
+        let exp = self.retry_policy.base_delay.saturating_mul(1u32 << attempt.saturating_sub(1).min(16));
+        let capped = exp.min(self.retry_policy.max_delay);
+        let jitter_ms = rand::thread_rng().gen_range(0..=capped.as_millis() as u64);
+        Duration::from_millis(jitter_ms)
+    }
+
+    pub async fn create_profile(
+        &self,
+        profile_data: HashMap<String, serde_json::Value>,
+        image_data: Option<(Vec<u8>, String)>, // (bytes, filename)
+    ) -> Result<Profile, ProfError> {
+        let (url, form) = self.build_profile_form("POST", profile_data, image_data).await?;
+        let response = self.send_once(self.client.post(&url).multipart(form)).await?;
+        parse_profile_response(response, "Not found").await
     }
 
     pub async fn update_profile(
@@ -200,86 +412,61 @@ impl ProfClient {
         profile_data: HashMap<String, serde_json::Value>,
         image_data: Option<(Vec<u8>, String)>,
     ) -> Result<Profile, ProfError> {
-        let auth = self.get_auth_params()?;
-        let uuid = auth.get("uuid").unwrap();
+        let (url, form) = self.build_profile_form("PUT", profile_data, image_data).await?;
+        let response = self.send_once(self.client.put(&url).multipart(form)).await?;
+        parse_profile_response(response, "Profile not found").await
+    }
+
+    /// Builds the signed multipart request shared by `create_profile` and
+    /// `update_profile`: a `profileData` field, the signed auth params, and
+    /// an optional image run through the image-processing pipeline.
+    /// `method` is only used for signing -- the caller still picks the
+    /// actual `reqwest` method to issue the request with.
+    async fn build_profile_form(
+        &self,
+        method: &str,
+        profile_data: HashMap<String, serde_json::Value>,
+        image_data: Option<(Vec<u8>, String)>,
+    ) -> Result<(String, multipart::Form), ProfError> {
+        let uuid = self.own_uuid()?;
+        let path = format!("/user/{}/profile", uuid);
+        let profile_json = serde_json::to_string(&profile_data)?;
+        let auth = self.get_auth_params(method, &path, "", profile_json.as_bytes())?;
 
-        let url = format!("{}/user/{}/profile", self.base_url, uuid);
+        let url = format!("{}{}", self.base_url, path);
 
         let mut form = multipart::Form::new();
 
         // Add profile data
-        form = form.text("profileData", serde_json::to_string(&profile_data)?);
+        form = form.text("profileData", profile_json);
 
         // Add auth parameters
         for (key, value) in auth {
             form = form.text(key, value);
         }
 
-        // Add image if provided
-        if let Some((image_bytes, filename)) = image_data {
+        // Add image if provided, running it through the image-processing
+        // pipeline when one is configured.
+        if let Some((image_bytes, filename, mime_type)) = self.process_upload_image(image_data)? {
             let part = multipart::Part::bytes(image_bytes)
-                .file_name(filename.clone())
-                .mime_str(&self.guess_mime_type(&filename))?;
+                .file_name(filename)
+                .mime_str(mime_type)?;
             form = form.part("image", part);
         }
 
-        let response = self.client
-            .put(&url)
-            .multipart(form)
-            .send()
-            .await?;
-
-        let status = response.status();
-        let response_text = response.text().await?;
-        
-        // Try to parse as ProfileResponse first (successful response)
-        let response_data: ProfileResponse = if let Ok(parsed) = serde_json::from_str(&response_text) {
-            parsed
-        } else {
-            // If that fails, try to parse as a simple error response
-            if let Ok(error_obj) = serde_json::from_str::<serde_json::Value>(&response_text) {
-                if let Some(error_msg) = error_obj.get("error").and_then(|e| e.as_str()) {
-                    ProfileResponse {
-                        success: false,
-                        profile: None,
-                        error: Some(error_msg.to_string()),
-                        details: error_obj.get("details")
-                            .and_then(|d| d.as_array())
-                            .map(|arr| arr.iter()
-                                .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                                .collect()),
-                    }
-                } else {
-                    return Err(ProfError::Service(format!("Invalid response format: {}", response_text)));
-                }
-            } else {
-                return Err(ProfError::Service(format!("Could not parse response: {}", response_text)));
-            }
-        };
-
-        if !response_data.success {
-            return match status.as_u16() {
-                400 => {
-                    if let Some(details) = response_data.details {
-                        Err(ProfError::Validation { errors: details })
-                    } else {
-                        Err(ProfError::Service(response_data.error.unwrap_or_else(|| "Validation failed".to_string())))
-                    }
-                },
-                404 => Err(ProfError::NotFound(response_data.error.unwrap_or_else(|| "Profile not found".to_string()))),
-                _ => Err(ProfError::Service(response_data.error.unwrap_or_else(|| "Unknown error".to_string()))),
-            };
-        }
-
-        response_data.profile.ok_or_else(|| ProfError::Service("No profile in response".to_string()))
+        Ok((url, form))
     }
 
     pub async fn get_profile(&self, uuid: Option<&str>) -> Result<Profile, ProfError> {
-        let auth = self.get_auth_params()?;
-        let target_uuid = uuid.unwrap_or_else(|| auth.get("uuid").unwrap());
+        let target_uuid = match uuid {
+            Some(u) => u.to_string(),
+            None => self.own_uuid()?,
+        };
+        let path = format!("/user/{}/profile", target_uuid);
+        let auth = self.get_auth_params("GET", &path, "", b"")?;
+
+        let mut url = format!("{}{}", self.base_url, path);
 
-        let mut url = format!("{}/user/{}/profile", self.base_url, target_uuid);
-        
         // Add query parameters
         let query_params: Vec<String> = auth.iter()
             .map(|(k, v)| format!("{}={}", k, v))
@@ -290,63 +477,31 @@ impl ProfClient {
             url.push_str(&query_params.join("&"));
         }
 
-        let response = self.client
-            .get(&url)
-            .send()
-            .await?;
-
-        let status = response.status();
-        let response_text = response.text().await?;
-        
-        // Try to parse as ProfileResponse first (successful response)
-        let response_data: ProfileResponse = if let Ok(parsed) = serde_json::from_str(&response_text) {
-            parsed
-        } else {
-            // If that fails, try to parse as a simple error response
-            if let Ok(error_obj) = serde_json::from_str::<serde_json::Value>(&response_text) {
-                if let Some(error_msg) = error_obj.get("error").and_then(|e| e.as_str()) {
-                    ProfileResponse {
-                        success: false,
-                        profile: None,
-                        error: Some(error_msg.to_string()),
-                        details: error_obj.get("details")
-                            .and_then(|d| d.as_array())
-                            .map(|arr| arr.iter()
-                                .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                                .collect()),
-                    }
-                } else {
-                    return Err(ProfError::Service(format!("Invalid response format: {}", response_text)));
-                }
-            } else {
-                return Err(ProfError::Service(format!("Could not parse response: {}", response_text)));
-            }
-        };
-
-        if !response_data.success {
-            return match status.as_u16() {
-                404 => Err(ProfError::NotFound(response_data.error.unwrap_or_else(|| "Profile not found".to_string()))),
-                _ => Err(ProfError::Service(response_data.error.unwrap_or_else(|| "Unknown error".to_string()))),
-            };
-        }
-
-        response_data.profile.ok_or_else(|| ProfError::Service("No profile in response".to_string()))
+        let response = self.send_idempotent(|| self.client.get(&url)).await?;
+        parse_profile_response(response, "Profile not found").await
     }
 
     pub async fn delete_profile(&self) -> Result<(), ProfError> {
-        let auth = self.get_auth_params()?;
-        let uuid = auth.get("uuid").unwrap();
+        let uuid = self.own_uuid()?;
+        let path = format!("/user/{}/profile", uuid);
+        let auth = self.get_auth_params("DELETE", &path, "", b"")?;
 
-        let url = format!("{}/user/{}/profile", self.base_url, uuid);
+        let mut url = format!("{}{}", self.base_url, path);
 
-        let response = self.client
-            .delete(&url)
-            .json(&auth)
-            .send()
-            .await?;
+        // Add query parameters
+        let query_params: Vec<String> = auth.iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect();
+
+        if !query_params.is_empty() {
+            url.push('?');
+            url.push_str(&query_params.join("&"));
+        }
+
+        let response = self.send_once(self.client.delete(&url)).await?;
 
         let status = response.status();
-        
+
         if !status.is_success() {
             let error_response: ProfileResponse = response.json().await?;
             return Err(ProfError::Service(error_response.error.unwrap_or_else(|| "Delete failed".to_string())));
@@ -356,11 +511,15 @@ impl ProfClient {
     }
 
     pub async fn get_profile_image(&self, uuid: Option<&str>) -> Result<Vec<u8>, ProfError> {
-        let auth = self.get_auth_params()?;
-        let target_uuid = uuid.unwrap_or_else(|| auth.get("uuid").unwrap());
+        let target_uuid = match uuid {
+            Some(u) => u.to_string(),
+            None => self.own_uuid()?,
+        };
+        let path = format!("/user/{}/profile/image", target_uuid);
+        let auth = self.get_auth_params("GET", &path, "", b"")?;
+
+        let mut url = format!("{}{}", self.base_url, path);
 
-        let mut url = format!("{}/user/{}/profile/image", self.base_url, target_uuid);
-        
         // Add query parameters
         let query_params: Vec<String> = auth.iter()
             .map(|(k, v)| format!("{}={}", k, v))
@@ -371,25 +530,30 @@ impl ProfClient {
             url.push_str(&query_params.join("&"));
         }
 
-        let response = self.client
-            .get(&url)
-            .send()
-            .await?;
+        let response = self.send_idempotent(|| self.client.get(&url)).await?;
 
-        if !response.status().is_success() {
+        let status = response.status();
+        if status.as_u16() == 404 {
             return Err(ProfError::NotFound("Image not found".to_string()));
         }
+        if !status.is_success() {
+            return Err(ProfError::Service(format!("Failed to fetch image (status {})", status.as_u16())));
+        }
 
         let bytes = response.bytes().await?;
         Ok(bytes.to_vec())
     }
 
     pub fn get_profile_image_url(&self, uuid: Option<&str>) -> Result<String, ProfError> {
-        let auth = self.get_auth_params()?;
-        let target_uuid = uuid.unwrap_or_else(|| auth.get("uuid").unwrap());
+        let target_uuid = match uuid {
+            Some(u) => u.to_string(),
+            None => self.own_uuid()?,
+        };
+        let path = format!("/user/{}/profile/image", target_uuid);
+        let auth = self.get_auth_params("GET", &path, "", b"")?;
+
+        let mut url = format!("{}{}", self.base_url, path);
 
-        let mut url = format!("{}/user/{}/profile/image", self.base_url, target_uuid);
-        
         // Add query parameters
         let query_params: Vec<String> = auth.iter()
             .map(|(k, v)| format!("{}={}", k, v))
@@ -405,11 +569,8 @@ impl ProfClient {
 
     pub async fn health_check(&self) -> Result<HealthResponse, ProfError> {
         let url = format!("{}/health", self.base_url);
-        
-        let response = self.client
-            .get(&url)
-            .send()
-            .await?;
+
+        let response = self.send_idempotent(|| self.client.get(&url)).await?;
 
         let health: HealthResponse = response.json().await?;
         Ok(health)
@@ -420,19 +581,25 @@ impl ProfClient {
         spell_name: &str,
         spell_data: HashMap<String, serde_json::Value>,
     ) -> Result<MagicResponse, ProfError> {
-        let auth = self.get_auth_params()?;
-        let url = format!("{}/magic/spell/{}", self.base_url, spell_name);
-
-        let mut request_data = spell_data;
-        for (key, value) in auth {
-            request_data.insert(key, serde_json::Value::String(value));
-        }
-
-        let response = self.client
-            .post(&url)
-            .json(&request_data)
-            .send()
-            .await?;
+        let path = format!("/magic/spell/{}", spell_name);
+        let spell_json = serde_json::to_vec(&spell_data)?;
+        let auth = self.get_auth_params("POST", &path, "", &spell_json)?;
+        let auth_json = serde_json::to_vec(&auth)?;
+        let url = format!("{}{}", self.base_url, path);
+
+        // Merge spell_data and auth into one flat object at the byte level
+        // instead of re-inserting the auth fields into spell_data and
+        // re-serializing it, which would require the server to reconstruct
+        // spell_data's original HashMap iteration order to verify the
+        // digest. This keeps the same flat, merged wire shape every other
+        // endpoint uses, while still letting the receiver strip the known
+        // auth keys and recover exactly the bytes the digest was computed
+        // over.
+        let body = merge_json_objects(&spell_json, &auth_json);
+
+        let response = self.send_once(
+            self.client.post(&url).header("Content-Type", "application/json").body(body)
+        ).await?;
 
         let status = response.status();
         let response_data: MagicResponse = response.json().await?;
@@ -444,6 +611,132 @@ impl ProfClient {
         Ok(response_data)
     }
 
+    /// Runs an optional image upload through the configured image-processing
+    /// pipeline (when the `image` feature is enabled and limits are set),
+    /// falling back to the raw bytes with an extension-guessed mime type
+    /// otherwise.
+    #[cfg(feature = "image")]
+    fn process_upload_image(
+        &self,
+        image_data: Option<(Vec<u8>, String)>,
+    ) -> Result<Option<(Vec<u8>, String, &'static str)>, ProfError> {
+        match (image_data, self.image_limits) {
+            (Some((bytes, filename)), Some(limits)) => {
+                let processed = image_processing::process_image(&bytes, limits)?;
+                Ok(Some((processed.bytes, filename, processed.mime_type)))
+            }
+            (Some((bytes, filename)), None) => {
+                let mime_type = self.guess_mime_type(&filename);
+                Ok(Some((bytes, filename, mime_type)))
+            }
+            (None, _) => Ok(None),
+        }
+    }
+
+    #[cfg(not(feature = "image"))]
+    fn process_upload_image(
+        &self,
+        image_data: Option<(Vec<u8>, String)>,
+    ) -> Result<Option<(Vec<u8>, String, &'static str)>, ProfError> {
+        Ok(image_data.map(|(bytes, filename)| {
+            let mime_type = self.guess_mime_type(&filename);
+            (bytes, filename, mime_type)
+        }))
+    }
+
+    /// Lists profiles with no filters applied, using the default page size.
+    pub async fn list_profiles(&self) -> Result<ProfilePage, ProfError> {
+        self.search_profiles(ProfileSearch::new()).await
+    }
+
+    /// Lists profiles matching `search`'s filters, returning the first page.
+    /// Call `next_page`/`prev_page` on the returned `ProfilePage` to walk the
+    /// rest of the directory.
+    pub async fn search_profiles(&self, search: ProfileSearch) -> Result<ProfilePage, ProfError> {
+        let path = "/profiles".to_string();
+        let query_params = search.into_query_params();
+        self.request_profile_page(&path, query_params).await
+    }
+
+    /// Re-issues a request for a `next`/`prev` cursor URL returned by a
+    /// previous page, re-signing it fresh via `get_auth_params`.
+    async fn fetch_profile_page(&self, cursor: &str) -> Result<ProfilePage, ProfError> {
+        let (path, query_params) = self.split_cursor(cursor)?;
+        self.request_profile_page(&path, query_params).await
+    }
+
+    async fn request_profile_page(
+        &self,
+        path: &str,
+        mut query_params: Vec<(String, String)>,
+    ) -> Result<ProfilePage, ProfError> {
+        let query = canonical_query_string(&query_params);
+        let auth = self.get_auth_params("GET", path, &query, b"")?;
+        query_params.extend(auth);
+
+        let mut url = format!("{}{}", self.base_url, path);
+        if !query_params.is_empty() {
+            let query: Vec<String> = query_params.iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect();
+            url.push('?');
+            url.push_str(&query.join("&"));
+        }
+
+        let response = self.send_idempotent(|| self.client.get(&url)).await?;
+
+        let status = response.status();
+        let link_header = response.headers()
+            .get("link")
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string());
+
+        let response_data: ProfilesResponse = response.json().await?;
+
+        if !response_data.success {
+            return Err(ProfError::Service(response_data.error.unwrap_or_else(|| {
+                format!("Failed to list profiles (status {})", status.as_u16())
+            })));
+        }
+
+        let (next, prev) = match link_header.as_deref().map(parse_link_header) {
+            Some(links) => links,
+            None => {
+                let links = response_data.links.unwrap_or_default();
+                (links.next, links.prev)
+            }
+        };
+
+        Ok(ProfilePage {
+            profiles: response_data.profiles.unwrap_or_default(),
+            next,
+            prev,
+        })
+    }
+
+    /// Splits a server-returned cursor URL (absolute or relative) into a path
+    /// and its existing query parameters, so pagination cursors survive
+    /// being re-signed.
+    fn split_cursor(&self, cursor: &str) -> Result<(String, Vec<(String, String)>), ProfError> {
+        let full_url = if cursor.starts_with("http://") || cursor.starts_with("https://") {
+            cursor.to_string()
+        } else if let Some(stripped) = cursor.strip_prefix(&self.base_url) {
+            format!("{}{}", self.base_url, stripped)
+        } else {
+            format!("{}{}", self.base_url, cursor)
+        };
+
+        let parsed = reqwest::Url::parse(&full_url)
+            .map_err(|e| ProfError::Service(format!("Invalid pagination cursor: {e}")))?;
+
+        let path = parsed.path().to_string();
+        let query_params: Vec<(String, String)> = parsed.query_pairs()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
+        Ok((path, query_params))
+    }
+
     fn guess_mime_type(&self, filename: &str) -> &'static str {
         let extension = filename.split('.').last().unwrap_or("").to_lowercase();
         match extension.as_str() {
@@ -488,10 +781,147 @@ impl ProfileBuilder {
     }
 }
 
+/// Parses a numeric `Retry-After` header (in seconds) on a `429`/`503`
+/// response. HTTP-date values aren't supported since the server only ever
+/// sends delta-seconds in practice.
+fn parse_retry_after(response: &reqwest::Response) -> Option<Duration> {
+    response.headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+/// Shared response parsing for the profile-returning endpoints
+/// (`create_profile`, `update_profile`, `get_profile`): the server wraps a
+/// successful result in a `ProfileResponse`, but falls back to a bare
+/// `{"error": ...}` object on some failure paths.
+async fn parse_profile_response(response: reqwest::Response, default_not_found: &str) -> Result<Profile, ProfError> {
+    let status = response.status();
+    let response_text = response.text().await?;
+
+    // Try to parse as ProfileResponse first (successful response)
+    let response_data: ProfileResponse = if let Ok(parsed) = serde_json::from_str(&response_text) {
+        parsed
+    } else if let Ok(error_obj) = serde_json::from_str::<serde_json::Value>(&response_text) {
+        // If that fails, try to parse as a simple error response
+        if let Some(error_msg) = error_obj.get("error").and_then(|e| e.as_str()) {
+            ProfileResponse {
+                success: false,
+                profile: None,
+                error: Some(error_msg.to_string()),
+                details: error_obj.get("details")
+                    .and_then(|d| d.as_array())
+                    .map(|arr| arr.iter()
+                        .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                        .collect()),
+            }
+        } else {
+            return Err(ProfError::Service(format!("Invalid response format: {}", response_text)));
+        }
+    } else {
+        return Err(ProfError::Service(format!("Could not parse response: {}", response_text)));
+    };
+
+    if !response_data.success {
+        return match status.as_u16() {
+            400 => {
+                if let Some(details) = response_data.details {
+                    Err(ProfError::Validation { errors: details })
+                } else {
+                    Err(ProfError::Service(response_data.error.unwrap_or_else(|| "Validation failed".to_string())))
+                }
+            },
+            404 => Err(ProfError::NotFound(response_data.error.unwrap_or_else(|| default_not_found.to_string()))),
+            _ => Err(ProfError::Service(response_data.error.unwrap_or_else(|| "Unknown error".to_string()))),
+        };
+    }
+
+    response_data.profile.ok_or_else(|| ProfError::Service("No profile in response".to_string()))
+}
+
+/// Canonicalizes query parameters for inclusion in a signing string: sorted
+/// by key (then value) so the same set of parameters always produces the
+/// same string regardless of the order the caller built them in.
+fn canonical_query_string(params: &[(String, String)]) -> String {
+    let mut sorted = params.to_vec();
+    sorted.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+    sorted.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join("&")
+}
+
+/// Splices two serialized JSON objects together into one flat object,
+/// instead of re-inserting one's fields into the other as a map and
+/// re-serializing -- which wouldn't necessarily preserve `first`'s exact
+/// byte representation (e.g. for a `HashMap`-backed object, whose
+/// iteration order isn't guaranteed to be stable across a second pass).
+fn merge_json_objects(first: &[u8], second: &[u8]) -> Vec<u8> {
+    debug_assert!(first.starts_with(b"{") && first.ends_with(b"}"));
+    debug_assert!(second.starts_with(b"{") && second.ends_with(b"}"));
+
+    let mut merged = Vec::with_capacity(first.len() + second.len());
+    merged.extend_from_slice(&first[..first.len() - 1]);
+    if first.len() > 2 {
+        merged.push(b',');
+    }
+    merged.extend_from_slice(&second[1..]);
+    merged
+}
+
+/// Parses a `Link` header of the form
+/// `<url>; rel="next", <url>; rel="prev"` into `(next, prev)` cursor URLs.
+fn parse_link_header(header: &str) -> (Option<String>, Option<String>) {
+    let mut next = None;
+    let mut prev = None;
+
+    for link in header.split(',') {
+        let mut parts = link.split(';');
+        let url = match parts.next() {
+            Some(u) => u.trim().trim_start_matches('<').trim_end_matches('>'),
+            None => continue,
+        };
+
+        for param in parts {
+            let param = param.trim();
+            if let Some(rel) = param.strip_prefix("rel=") {
+                match rel.trim_matches('"') {
+                    "next" => next = Some(url.to_string()),
+                    "prev" | "previous" => prev = Some(url.to_string()),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    (next, prev)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_link_header() {
+        let header = r#"<https://prof.example/profiles?after=abc>; rel="next", <https://prof.example/profiles?before=xyz>; rel="prev""#;
+        let (next, prev) = parse_link_header(header);
+        assert_eq!(next.as_deref(), Some("https://prof.example/profiles?after=abc"));
+        assert_eq!(prev.as_deref(), Some("https://prof.example/profiles?before=xyz"));
+    }
+
+    #[test]
+    fn test_profile_search_query_params() {
+        let params = ProfileSearch::new()
+            .name_prefix("ali")
+            .email_domain("example.com")
+            .page_size(25)
+            .field("team", "eng")
+            .into_query_params();
+
+        assert!(params.contains(&("namePrefix".to_string(), "ali".to_string())));
+        assert!(params.contains(&("emailDomain".to_string(), "example.com".to_string())));
+        assert!(params.contains(&("pageSize".to_string(), "25".to_string())));
+        assert!(params.contains(&("team".to_string(), "eng".to_string())));
+    }
+
     #[test]
     fn test_profile_builder() {
         let profile_data = ProfileBuilder::new()
@@ -507,6 +937,87 @@ mod tests {
         assert_eq!(profile_data.get("age").unwrap(), &serde_json::Value::Number(serde_json::Number::from(30)));
     }
 
+    #[test]
+    fn test_get_auth_params_binds_method_path_and_body() {
+        let client = ProfClient::new("http://localhost:3007".to_string())
+            .with_sessionless(Sessionless::new());
+
+        let auth_a = client.get_auth_params("POST", "/user/abc/profile", "", b"{\"name\":\"a\"}").unwrap();
+        let auth_b = client.get_auth_params("POST", "/user/abc/profile", "", b"{\"name\":\"b\"}").unwrap();
+        let auth_c = client.get_auth_params("PUT", "/user/abc/profile", "", b"{\"name\":\"a\"}").unwrap();
+
+        // Same uuid regardless of request, but the digest (and therefore the
+        // signature) must change whenever the body or method changes.
+        assert_eq!(auth_a.get("uuid"), auth_b.get("uuid"));
+        assert_ne!(auth_a.get("digest"), auth_b.get("digest"));
+        assert_ne!(auth_a.get("signature"), auth_b.get("signature"));
+        assert_ne!(auth_a.get("digest"), auth_c.get("digest"));
+        assert_ne!(auth_a.get("signature"), auth_c.get("signature"));
+
+        let mut hasher = Sha256::new();
+        hasher.update(b"{\"name\":\"a\"}");
+        let expected_digest = format!("SHA-256={}", BASE64.encode(hasher.finalize()));
+        assert_eq!(auth_a.get("digest"), Some(&expected_digest));
+    }
+
+    #[test]
+    fn test_get_auth_params_binds_query_string() {
+        let client = ProfClient::new("http://localhost:3007".to_string())
+            .with_sessionless(Sessionless::new());
+
+        let auth_a = client.get_auth_params("GET", "/profiles", "namePrefix=ali", b"").unwrap();
+        let auth_b = client.get_auth_params("GET", "/profiles", "namePrefix=bob", b"").unwrap();
+        let auth_none = client.get_auth_params("GET", "/profiles", "", b"").unwrap();
+
+        // A signature captured for one set of filters must not verify
+        // against a request for a different set of filters.
+        assert_ne!(auth_a.get("signature"), auth_b.get("signature"));
+        assert_ne!(auth_a.get("signature"), auth_none.get("signature"));
+    }
+
+    #[test]
+    fn test_canonical_query_string_is_order_independent() {
+        let a = vec![
+            ("namePrefix".to_string(), "ali".to_string()),
+            ("pageSize".to_string(), "25".to_string()),
+        ];
+        let b = vec![
+            ("pageSize".to_string(), "25".to_string()),
+            ("namePrefix".to_string(), "ali".to_string()),
+        ];
+        assert_eq!(canonical_query_string(&a), canonical_query_string(&b));
+    }
+
+    #[test]
+    fn test_merge_json_objects_flattens_fields_from_both() {
+        let merged = merge_json_objects(br#"{"a":1}"#, br#"{"b":2}"#);
+        assert_eq!(merged, br#"{"a":1,"b":2}"#);
+
+        let value: serde_json::Value = serde_json::from_slice(&merged).unwrap();
+        assert_eq!(value["a"], 1);
+        assert_eq!(value["b"], 2);
+    }
+
+    #[test]
+    fn test_merge_json_objects_handles_empty_first_object() {
+        let merged = merge_json_objects(b"{}", br#"{"b":2}"#);
+        assert_eq!(merged, br#"{"b":2}"#);
+    }
+
+    #[test]
+    fn test_backoff_delay_stays_within_bounds() {
+        let client = ProfClient::new("http://localhost:3007".to_string()).with_retry(RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+        });
+
+        for attempt in 1..=20u32 {
+            let delay = client.backoff_delay(attempt);
+            assert!(delay <= client.retry_policy.max_delay, "attempt {attempt} delay {delay:?} exceeded max_delay");
+        }
+    }
+
     #[tokio::test]
     async fn test_client_creation() {
         let client = ProfClient::new("http://localhost:3007".to_string());